@@ -0,0 +1,123 @@
+//! Optional transport/storage container around the raw bitstream.
+//!
+//! [`encode_compressed`]/[`decode_compressed`] wrap the output of
+//! [`encode`](crate::encode) in a short header — magic, algorithm byte, and
+//! the uncompressed length — and run it through DEFLATE or zstd. The core
+//! [`encode`](crate::encode)/[`decode`](crate::decode) codecs are left
+//! untouched; this just gives callers a single call per file and lets them
+//! pick the codec.
+
+use std::io::{Read, Write};
+
+use crate::{decode, encode, Character, Error, Result};
+
+/// Format identifier stamped at the start of every container.
+const MAGIC: [u8; 3] = *b"PTX";
+
+/// Compression codec selected per file.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Algorithm {
+    Deflate = 0,
+    Zstd = 1,
+}
+
+impl Algorithm {
+    fn from_byte(byte: u8) -> Option<Self> {
+        match byte {
+            0 => Some(Self::Deflate),
+            1 => Some(Self::Zstd),
+            _ => None,
+        }
+    }
+}
+
+/// Encode `chars` and wrap the bitstream in a compressed container.
+pub fn encode_compressed(chars: &[Character], algorithm: Algorithm) -> Vec<u8> {
+    let raw = encode(chars);
+    let body = match algorithm {
+        Algorithm::Deflate => {
+            let mut encoder =
+                flate2::write::DeflateEncoder::new(Vec::new(), flate2::Compression::default());
+            encoder.write_all(&raw).unwrap();
+            encoder.finish().unwrap()
+        }
+        Algorithm::Zstd => zstd::encode_all(&raw[..], 0).unwrap(),
+    };
+    let mut output = Vec::with_capacity(8 + body.len());
+    output.extend_from_slice(&MAGIC);
+    output.push(algorithm as u8);
+    output.extend_from_slice(&(raw.len() as u32).to_le_bytes());
+    output.extend_from_slice(&body);
+    output
+}
+
+/// Read a container header, inflate into a scratch buffer, and delegate to
+/// [`decode`](crate::decode). Malformed headers surface as [`Error`] rather
+/// than panicking, since the bytes come from untrusted files.
+pub fn decode_compressed(input: &[u8]) -> Result<Vec<Character>> {
+    if input.len() < 8 {
+        return Err(Error::UnexpectedEof);
+    }
+    if input[..3] != MAGIC {
+        return Err(Error::BadMagic);
+    }
+    let algorithm = Algorithm::from_byte(input[3]).ok_or(Error::UnknownAlgorithm)?;
+    let length = u32::from_le_bytes(input[4..8].try_into().unwrap()) as usize;
+    let body = &input[8..];
+    let scratch = match algorithm {
+        Algorithm::Deflate => {
+            let mut out = Vec::with_capacity(length);
+            flate2::read::DeflateDecoder::new(body)
+                .read_to_end(&mut out)
+                .map_err(|_| Error::UnexpectedEof)?;
+            out
+        }
+        Algorithm::Zstd => zstd::decode_all(body).map_err(|_| Error::UnexpectedEof)?,
+    };
+    Ok(decode(&scratch))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> Vec<Character> {
+        vec![
+            Character {
+                x_offset: 2,
+                y_offset: -3,
+                control_code: None,
+                graphical_data: Some([0xABu8; 512]),
+            },
+            Character {
+                x_offset: 0,
+                y_offset: 0,
+                control_code: None,
+                graphical_data: Some([0u8; 512]),
+            },
+        ]
+    }
+
+    #[test]
+    fn round_trip_through_both_codecs() {
+        let chars = sample();
+        for algorithm in [Algorithm::Deflate, Algorithm::Zstd] {
+            let bytes = encode_compressed(&chars, algorithm);
+            assert_eq!(&bytes[..3], &MAGIC);
+            assert_eq!(decode_compressed(&bytes).unwrap(), chars);
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_headers() {
+        assert_eq!(decode_compressed(b"PT").unwrap_err(), Error::UnexpectedEof);
+        assert_eq!(
+            decode_compressed(b"XXX\0\0\0\0\0").unwrap_err(),
+            Error::BadMagic
+        );
+        assert_eq!(
+            decode_compressed(b"PTX\x07\0\0\0\0").unwrap_err(),
+            Error::UnknownAlgorithm
+        );
+    }
+}
@@ -24,45 +24,178 @@ renderer. May utilize offsets for extra data or interpretation of whitespace,
 eg "x > 0" means space or "y < 0" means newline.
 */
 
-use bitstream_io::{BigEndian, BitRead, BitReader, Endianness};
-use std::io::Read;
+#![cfg_attr(not(feature = "std"), no_std)]
 
-pub struct BitReaderWrapper<T: Read, E: Endianness> {
+extern crate alloc;
+
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+
+#[cfg(feature = "std")]
+pub mod container;
+pub mod render;
+
+/// Errors surfaced by the codec. Kept `core`-only so the reader works in
+/// `no_std` contexts; [`Read`](std::io::Read) failures collapse to
+/// [`Error::UnexpectedEof`] at the [`ByteSource`] boundary.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Error {
+    UnexpectedEof,
+    /// Container header did not start with the expected magic bytes.
+    BadMagic,
+    /// Container header named a compression algorithm this build doesn't know.
+    UnknownAlgorithm,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::UnexpectedEof => f.write_str("unexpected end of input"),
+            Error::BadMagic => f.write_str("not a pictext container"),
+            Error::UnknownAlgorithm => f.write_str("unknown compression algorithm"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
+
+pub type Result<T> = core::result::Result<T, Error>;
+
+/// Minimal byte feed backing the bit reader, in place of [`std::io::Read`] so
+/// the codec can run in embedded/WASM builds.
+pub trait ByteSource {
+    fn read_byte(&mut self) -> Result<u8>;
+}
+
+/// A [`ByteSource`] over an in-memory slice.
+pub struct SliceSource<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> SliceSource<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, position: 0 }
+    }
+}
+
+impl ByteSource for SliceSource<'_> {
+    fn read_byte(&mut self) -> Result<u8> {
+        let byte = self
+            .bytes
+            .get(self.position)
+            .copied()
+            .ok_or(Error::UnexpectedEof)?;
+        self.position += 1;
+        Ok(byte)
+    }
+}
+
+/// Adapts any [`Read`](std::io::Read) into a [`ByteSource`]; only available
+/// with the default `std` feature.
+#[cfg(feature = "std")]
+pub struct ReadSource<R: std::io::Read>(pub R);
+
+#[cfg(feature = "std")]
+impl<R: std::io::Read> ByteSource for ReadSource<R> {
+    fn read_byte(&mut self) -> Result<u8> {
+        let mut buf = [0u8; 1];
+        match std::io::Read::read_exact(&mut self.0, &mut buf) {
+            Ok(()) => Ok(buf[0]),
+            Err(_) => Err(Error::UnexpectedEof),
+        }
+    }
+}
+
+pub struct BitReaderWrapper<S: ByteSource> {
     bit_count: usize,
-    bitreader: BitReader<T, E>,
+    source: S,
+    current: u8,
+    bits_left: u8,
+}
+
+pub struct BitWriterWrapper {
+    bit_count: usize,
+    sink: Vec<u8>,
+    current: u8,
+    filled: u8,
 }
 
 // Subject to change
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub enum ControlCode {
+    VariableOffsets = 3,
     DirectionRightDown = 4,
     DirectionLeftDown = 5,
     DirectionRightUp = 6,
     DirectionLeftUp = 7,
 }
 
-impl<T: Read, E: Endianness> BitRead for BitReaderWrapper<T, E> {
-    fn read_bit(&mut self) -> std::io::Result<bool> {
-        if self.bit_count % 8 == 0 {
-            self.bitreader.read_bit().ok();
-            self.bit_count += 1;
+impl ControlCode {
+    pub fn from_code(code: u8) -> Option<Self> {
+        match code {
+            3 => Some(Self::VariableOffsets),
+            4 => Some(Self::DirectionRightDown),
+            5 => Some(Self::DirectionLeftDown),
+            6 => Some(Self::DirectionRightUp),
+            7 => Some(Self::DirectionLeftUp),
+            _ => None,
         }
+    }
+}
+
+impl<S: ByteSource> BitReaderWrapper<S> {
+    pub fn new(source: S) -> Self {
+        Self {
+            bit_count: 0,
+            source,
+            current: 0,
+            bits_left: 0,
+        }
+    }
+
+    /// Pull one raw bit, big-endian, refilling from the [`ByteSource`] as
+    /// byte boundaries are crossed. Does not touch the continuation framing.
+    fn raw_bit(&mut self) -> Result<bool> {
+        if self.bits_left == 0 {
+            self.current = self.source.read_byte()?;
+            self.bits_left = 8;
+        }
+        self.bits_left -= 1;
+        Ok((self.current >> self.bits_left) & 1 == 1)
+    }
+
+    /// Consume the per-octet continuation bit sitting at a framed boundary.
+    /// The format sets it on every octet that continues a character, so a
+    /// clear bit here means the framing is broken; this is checked only by a
+    /// debug assertion, and release builds drop the bit unchecked. A truncated
+    /// stream (no bit at all) is not reported here: the payload read that
+    /// follows returns [`Error::UnexpectedEof`].
+    fn consume_continuation(&mut self) {
+        let framed = self.raw_bit().unwrap_or(true);
+        debug_assert!(framed, "octet continuation bit cleared mid-character");
         self.bit_count += 1;
-        self.bitreader.read_bit()
     }
 
-    fn read<U>(&mut self, mut bits: u32) -> std::io::Result<U>
-    where
-        U: bitstream_io::Numeric,
-    {
-        let mut n = U::default();
+    fn read_bit(&mut self) -> Result<bool> {
+        if self.bit_count.is_multiple_of(8) {
+            self.consume_continuation();
+        }
+        self.bit_count += 1;
+        self.raw_bit()
+    }
+
+    fn read_bits(&mut self, mut bits: u32) -> Result<u32> {
+        let mut n = 0u32;
         while bits > 0 {
-            if self.bit_count % 8 == 0 {
-                self.bitreader.read_bit().ok();
-                self.bit_count += 1;
+            if self.bit_count.is_multiple_of(8) {
+                self.consume_continuation();
             }
             n <<= 1;
-            if self.bitreader.read_bit().unwrap() {
-                n |= U::ONE;
+            if self.raw_bit()? {
+                n |= 1;
             }
             self.bit_count += 1;
             bits -= 1;
@@ -70,78 +203,224 @@ impl<T: Read, E: Endianness> BitRead for BitReaderWrapper<T, E> {
         Ok(n)
     }
 
-    fn read_signed<S>(&mut self, bits: u32) -> std::io::Result<S>
-    where
-        S: bitstream_io::SignedNumeric,
-    {
-        if self.bit_count % 8 == 0 {
-            self.bitreader.read_bit().ok();
-            self.bit_count += 1;
+    fn read_signed(&mut self, bits: u32) -> Result<i32> {
+        if self.bit_count.is_multiple_of(8) {
+            self.consume_continuation();
         }
-        let sign = self.bitreader.read_bit().unwrap();
+        let sign = self.raw_bit()?;
         self.bit_count += 1;
-        let n = self.read::<S>(bits - 1).unwrap();
+        let n = self.read_bits(bits - 1)? as i32;
         if sign {
-            Ok(n.as_negative(bits))
+            Ok(n - (1 << (bits - 1)))
         } else {
             Ok(n)
         }
     }
 
-    fn read_to<V>(&mut self) -> std::io::Result<V>
-    where
-        V: bitstream_io::Primitive,
-    {
-        unimplemented![]
+    fn skip(&mut self, bits: u32) -> Result<()> {
+        self.bit_count += bits as usize;
+        for _ in 0..bits {
+            self.raw_bit()?;
+        }
+        Ok(())
     }
 
-    fn read_as_to<F, V>(&mut self) -> std::io::Result<V>
-    where
-        F: Endianness,
-        V: bitstream_io::Primitive,
-    {
-        unimplemented![]
+    /// Whether the reader sits on a framed octet boundary, i.e. the next raw
+    /// bit is a per-octet continuation bit rather than payload.
+    pub fn byte_aligned(&self) -> bool {
+        self.bit_count.is_multiple_of(8)
     }
 
-    fn skip(&mut self, bits: u32) -> std::io::Result<()> {
-        self.bit_count += bits as usize;
-        self.bitreader.skip(bits)
+    /// Advance to the next framed octet boundary, discarding the remaining
+    /// payload bits of the current octet.
+    pub fn byte_align(&mut self) -> Result<()> {
+        if !self.byte_aligned() {
+            let remainder = (self.bit_count % 8) as u32;
+            self.skip(8 - remainder)?;
+        }
+        Ok(())
+    }
+
+    /// Read whole payload bytes, transparently consuming the per-octet
+    /// continuation bit that [`read_bit`](Self::read_bit) skips as each byte
+    /// boundary is crossed.
+    pub fn read_bytes(&mut self, buf: &mut [u8]) -> Result<()> {
+        for slot in buf.iter_mut() {
+            *slot = self.read_bits(8)? as u8;
+        }
+        Ok(())
+    }
+
+    /// Read a self-terminating zig-zag VLQ: little-endian 7-bit groups, each
+    /// preceded by a continuation bit, decoded from `(magnitude << 1) | sign`.
+    fn read_vlq(&mut self) -> i32 {
+        let mut unsigned = 0u32;
+        let mut shift = 0;
+        loop {
+            let more = self.read_bit().unwrap();
+            let group = self.read_bits(7).unwrap();
+            unsigned |= group << shift;
+            shift += 7;
+            if !more {
+                break;
+            }
+        }
+        let magnitude = (unsigned >> 1) as i32;
+        if unsigned & 1 == 1 {
+            -magnitude
+        } else {
+            magnitude
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<R: std::io::Read> BitReaderWrapper<ReadSource<R>> {
+    pub fn from_reader(reader: R) -> Self {
+        Self::new(ReadSource(reader))
+    }
+}
+
+impl Default for BitWriterWrapper {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BitWriterWrapper {
+    pub fn new() -> Self {
+        Self {
+            bit_count: 0,
+            sink: Vec::new(),
+            current: 0,
+            filled: 0,
+        }
+    }
+
+    /// Push one raw bit, big-endian, flushing a full byte to the sink.
+    fn raw_bit(&mut self, bit: bool) {
+        self.current = (self.current << 1) | bit as u8;
+        self.filled += 1;
+        if self.filled == 8 {
+            self.sink.push(self.current);
+            self.current = 0;
+            self.filled = 0;
+        }
+    }
+
+    /// Emit a single bit, prefixing it with the per-octet continuation bit
+    /// whenever we cross a byte boundary so the stream stays framed exactly
+    /// the way `BitReaderWrapper::read_bit` consumes it.
+    fn write_bit(&mut self, bit: bool) -> Result<()> {
+        if self.bit_count.is_multiple_of(8) {
+            self.raw_bit(true);
+            self.bit_count += 1;
+        }
+        self.bit_count += 1;
+        self.raw_bit(bit);
+        Ok(())
+    }
+
+    fn write_bits(&mut self, mut bits: u32, value: u32) -> Result<()> {
+        while bits > 0 {
+            if self.bit_count.is_multiple_of(8) {
+                self.raw_bit(true);
+                self.bit_count += 1;
+            }
+            self.raw_bit((value >> (bits - 1)) & 1 == 1);
+            self.bit_count += 1;
+            bits -= 1;
+        }
+        Ok(())
     }
 
-    fn byte_aligned(&self) -> bool {
-        unimplemented![]
+    fn write_signed(&mut self, bits: u32, value: i8) -> Result<()> {
+        if self.bit_count.is_multiple_of(8) {
+            self.raw_bit(true);
+            self.bit_count += 1;
+        }
+        let sign = value < 0;
+        self.raw_bit(sign);
+        self.bit_count += 1;
+        let magnitude = if sign {
+            (value as i16 + (1 << (bits - 1))) as u32
+        } else {
+            value as u32
+        };
+        self.write_bits(bits - 1, magnitude)
     }
 
-    fn byte_align(&mut self) {
-        unimplemented![]
+    /// Write a self-terminating zig-zag VLQ; the exact inverse of
+    /// [`BitReaderWrapper::read_vlq`].
+    fn write_vlq(&mut self, value: i32) -> Result<()> {
+        let sign = (value < 0) as u32;
+        let mut unsigned = (value.unsigned_abs() << 1) | sign;
+        loop {
+            let group = unsigned & 0x7f;
+            unsigned >>= 7;
+            let more = unsigned != 0;
+            self.write_bit(more)?;
+            self.write_bits(7, group)?;
+            if !more {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Emit `bits` raw padding bits without continuation framing, mirroring the
+    /// trailing `skip` `decode` performs to realign on the next byte boundary.
+    fn pad(&mut self, bits: u32) -> Result<()> {
+        for _ in 0..bits {
+            self.raw_bit(false);
+        }
+        self.bit_count += bits as usize;
+        Ok(())
+    }
+
+    /// Flush any buffered partial byte and return the written stream.
+    fn finish(mut self) -> Vec<u8> {
+        if self.filled > 0 {
+            self.current <<= 8 - self.filled;
+            self.sink.push(self.current);
+        }
+        self.sink
     }
 }
 
+#[derive(Debug, PartialEq)]
 pub struct Character {
-    pub x_offset: i8,
-    pub y_offset: i8,
+    pub x_offset: i16,
+    pub y_offset: i16,
     pub control_code: Option<u8>,
     pub graphical_data: Option<[u8; 512]>,
 }
 
 pub fn decode(input: &[u8]) -> Vec<Character> {
-    let mut input = BitReaderWrapper {
-        bit_count: 0,
-        bitreader: BitReader::<&[u8], BigEndian>::new(input),
-    };
+    let mut input = BitReaderWrapper::new(SliceSource::new(input));
     let mut output = vec![];
-    while let Ok(graphical) = input.bitreader.read_bit() {
+    let mut variable = false;
+    while let Ok(graphical) = input.raw_bit() {
         input.bit_count += 1;
         if graphical {
             let offset_x = input.read_bit().unwrap();
             let offset_y = input.read_bit().unwrap();
-            let mut x_offset = 0i8;
-            let mut y_offset = 0i8;
-            if offset_x {
-                x_offset = input.read::<i8>(7).unwrap();
-            }
-            if offset_y {
-                y_offset = input.read_signed::<i8>(7).unwrap();
+            let mut x_offset = 0i16;
+            let mut y_offset = 0i16;
+            if variable {
+                if offset_x {
+                    x_offset = input.read_vlq() as i16;
+                }
+                if offset_y {
+                    y_offset = input.read_vlq() as i16;
+                }
+            } else {
+                if offset_x {
+                    x_offset = input.read_bits(7).unwrap() as i16;
+                }
+                if offset_y {
+                    y_offset = input.read_signed(7).unwrap() as i16;
+                }
             }
             let mut map = [0u64; 64];
             let mut value = None;
@@ -161,13 +440,12 @@ pub fn decode(input: &[u8]) -> Vec<Character> {
                 )
             }) {
                 for i in 0..q.len() {
-                    if value.is_none() {
-                        if q.ends_with(&vec![0; q.len() - i]) {
-                            if input.read_bit().unwrap() {
-                                erase = i as i32;
-                                value = Some(input.read_bit().unwrap());
-                            }
-                        }
+                    if value.is_none()
+                        && q.ends_with(&[0usize; 6][i..])
+                        && input.read_bit().unwrap()
+                    {
+                        erase = i as i32;
+                        value = Some(input.read_bit().unwrap());
                     }
                 }
                 map[index] |= if let Some(v) = value {
@@ -182,16 +460,14 @@ pub fn decode(input: &[u8]) -> Vec<Character> {
                     0
                 };
                 for i in 0..q.len() {
-                    if q.ends_with(&vec![0; q.len() - i]) {
-                        if erase == i as i32 {
-                            value = None;
-                            erase = -1;
-                        }
+                    if q.ends_with(&[0usize; 6][i..]) && erase == i as i32 {
+                        value = None;
+                        erase = -1;
                     }
                 }
             }
             let mut raster_data: [u8; 512] = [0; 512];
-            let mut iter = map.iter().map(|t6| t6.to_le_bytes()).flatten();
+            let mut iter = map.iter().flat_map(|t6| t6.to_le_bytes());
             raster_data.fill_with(|| iter.next().unwrap());
             output.push(Character {
                 x_offset,
@@ -200,14 +476,218 @@ pub fn decode(input: &[u8]) -> Vec<Character> {
                 graphical_data: Some(raster_data),
             });
             input.skip(8 - input.bit_count as u32 % 8).ok();
+            debug_assert!(input.byte_aligned(), "glyph body left the stream unframed");
         } else {
+            let code = input.read_bits(7).unwrap() as u8;
+            if code == ControlCode::VariableOffsets as u8 {
+                variable = true;
+            }
             output.push(Character {
                 x_offset: 0,
                 y_offset: 0,
-                control_code: input.read::<u8>(7).unwrap().into(),
+                control_code: Some(code),
                 graphical_data: None,
             })
         }
     }
     output
 }
+
+/// Pixel `n` of a glyph, indexed the same way `decode` laid it out: row
+/// `n / 64`, bit `n % 64` of that row's little-endian `u64`.
+fn pixel(map: &[u64; 64], n: usize) -> bool {
+    (map[n / 64] >> (n % 64)) & 1 == 1
+}
+
+/// Whether the run `[n, n + len)` is a single fill value.
+fn homogeneous(map: &[u64; 64], n: usize, len: usize) -> bool {
+    let first = pixel(map, n);
+    (n..n + len).all(|m| pixel(map, m) == first)
+}
+
+/// Inverse of [`decode`]: re-emit the exact bitstream `decode` consumes.
+///
+/// The quadtree walk mirrors `decode`'s `0..4096` loop bit-for-bit so that
+/// `decode(encode(chars)) == chars` holds — coarsest qualifying level first,
+/// a `1` bit plus fill for a homogeneous run, a `0` bit to descend, and the
+/// raw pixel bit once no qualifying level remains.
+pub fn encode(chars: &[Character]) -> Vec<u8> {
+    let mut output = BitWriterWrapper::new();
+    let mut variable = false;
+    for character in chars {
+        if let Some(graphical) = character.graphical_data {
+            output.raw_bit(true);
+            output.bit_count += 1;
+            let offset_x = character.x_offset != 0;
+            let offset_y = character.y_offset != 0;
+            output.write_bit(offset_x).unwrap();
+            output.write_bit(offset_y).unwrap();
+            if variable {
+                if offset_x {
+                    output.write_vlq(character.x_offset as i32).unwrap();
+                }
+                if offset_y {
+                    output.write_vlq(character.y_offset as i32).unwrap();
+                }
+            } else {
+                if offset_x {
+                    output.write_bits(7, character.x_offset as u8 as u32).unwrap();
+                }
+                if offset_y {
+                    output.write_signed(7, character.y_offset as i8).unwrap();
+                }
+            }
+            let mut map = [0u64; 64];
+            for (row, slot) in map.iter_mut().enumerate() {
+                let bytes: [u8; 8] = graphical[row * 8..row * 8 + 8].try_into().unwrap();
+                *slot = u64::from_le_bytes(bytes);
+            }
+            let mut value = None;
+            let mut erase = -1;
+            for (n, q) in (0..4096).map(|n: usize| {
+                (
+                    n,
+                    [
+                        n / 1024 % 4,
+                        n / 256 % 4,
+                        n / 64 % 4,
+                        n / 16 % 4,
+                        n / 4 % 4,
+                        n % 4,
+                    ],
+                )
+            }) {
+                for i in 0..q.len() {
+                    if value.is_none() && q.ends_with(&[0usize; 6][i..]) {
+                        let run = 1usize << (2 * (q.len() - i));
+                        if homogeneous(&map, n, run) {
+                            output.write_bit(true).unwrap();
+                            erase = i as i32;
+                            let fill = pixel(&map, n);
+                            value = Some(fill);
+                            output.write_bit(fill).unwrap();
+                        } else {
+                            output.write_bit(false).unwrap();
+                        }
+                    }
+                }
+                if value.is_none() {
+                    output.write_bit(pixel(&map, n)).unwrap();
+                }
+                for i in 0..q.len() {
+                    if q.ends_with(&[0usize; 6][i..]) && erase == i as i32 {
+                        value = None;
+                        erase = -1;
+                    }
+                }
+            }
+            output.pad(8 - output.bit_count as u32 % 8).unwrap();
+        } else {
+            output.raw_bit(false);
+            output.bit_count += 1;
+            let code = character.control_code.unwrap_or(0);
+            if code == ControlCode::VariableOffsets as u8 {
+                variable = true;
+            }
+            output.write_bits(7, code as u32).unwrap();
+        }
+    }
+    output.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn checker_glyph() -> [u8; 512] {
+        let mut map = [0u64; 64];
+        for (row, slot) in map.iter_mut().enumerate() {
+            *slot = if row % 2 == 0 {
+                0x5555_5555_5555_5555
+            } else {
+                0xAAAA_AAAA_AAAA_AAAA
+            };
+        }
+        let mut raster = [0u8; 512];
+        let mut iter = map.iter().flat_map(|row| row.to_le_bytes());
+        raster.fill_with(|| iter.next().unwrap());
+        raster
+    }
+
+    #[test]
+    fn round_trip_blank_glyph() {
+        let chars = vec![Character {
+            x_offset: 3,
+            y_offset: -5,
+            control_code: None,
+            graphical_data: Some([0u8; 512]),
+        }];
+        assert_eq!(decode(&encode(&chars)), chars);
+    }
+
+    #[test]
+    fn round_trip_mixed_glyph() {
+        let chars = vec![Character {
+            x_offset: 0,
+            y_offset: 0,
+            control_code: None,
+            graphical_data: Some(checker_glyph()),
+        }];
+        assert_eq!(decode(&encode(&chars)), chars);
+    }
+
+    #[test]
+    fn round_trip_variable_offsets() {
+        let mut chars = vec![Character {
+            x_offset: 0,
+            y_offset: 0,
+            control_code: Some(ControlCode::VariableOffsets as u8),
+            graphical_data: None,
+        }];
+        for (x, y) in [(0i16, 0i16), (1, -1), (127, -64), (128, -128), (5000, -4096)] {
+            chars.push(Character {
+                x_offset: x,
+                y_offset: y,
+                control_code: None,
+                graphical_data: Some(checker_glyph()),
+            });
+        }
+        assert_eq!(decode(&encode(&chars)), chars);
+    }
+
+    #[test]
+    fn framed_bytes_round_trip() {
+        let payload = [0x12u8, 0x34, 0x56, 0x78, 0x9a, 0xbc];
+        let mut writer = BitWriterWrapper::new();
+        for &byte in &payload {
+            writer.write_bits(8, byte as u32).unwrap();
+        }
+        let encoded = writer.finish();
+
+        let mut reader = BitReaderWrapper::new(SliceSource::new(&encoded));
+        let mut out = [0u8; 6];
+        reader.read_bytes(&mut out).unwrap();
+        assert_eq!(out, payload);
+    }
+
+    #[test]
+    fn byte_align_reaches_boundary() {
+        let data = [0b1010_1010u8, 0b1100_1100];
+        let mut reader = BitReaderWrapper::new(SliceSource::new(&data));
+        reader.read_bits(3).unwrap();
+        assert!(!reader.byte_aligned());
+        reader.byte_align().unwrap();
+        assert!(reader.byte_aligned());
+    }
+
+    #[test]
+    #[should_panic(expected = "continuation bit cleared")]
+    fn broken_framing_is_rejected() {
+        // First octet frames correctly (leading bit set), but the second
+        // octet's continuation bit is clear — reading across the boundary
+        // must trip the framing assertion rather than silently continue.
+        let data = [0b1000_0000u8, 0b0000_0000];
+        let mut reader = BitReaderWrapper::new(SliceSource::new(&data));
+        reader.read_bytes(&mut [0u8; 1]).unwrap();
+    }
+}
@@ -0,0 +1,231 @@
+//! In-memory rasterizer for decoded [`Character`]s.
+//!
+//! [`render`] walks a glyph stream, advancing a cursor according to the
+//! [`ControlCode`] direction in force and each character's point offsets, and
+//! blits every glyph into a buffer that grows to fit the laid-out text so
+//! callers never precompute a canvas size.
+
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::{Character, ControlCode};
+
+/// Side length, in points, of every glyph.
+const GLYPH: i64 = 64;
+
+/// An RGB triple, reused as a grayscale level via its first channel.
+pub type Color = [u8; 3];
+
+pub struct RenderOptions {
+    pub foreground: Color,
+    pub background: Color,
+    /// Integer upscaling factor; each point becomes a `scale`×`scale` block.
+    pub scale: usize,
+    /// Emit a single-channel buffer instead of RGB.
+    pub grayscale: bool,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        Self {
+            foreground: [0, 0, 0],
+            background: [255, 255, 255],
+            scale: 1,
+            grayscale: false,
+        }
+    }
+}
+
+pub struct ImageBuffer {
+    pub width: usize,
+    pub height: usize,
+    pub channels: usize,
+    pub data: Vec<u8>,
+}
+
+/// One glyph scheduled for drawing at an absolute point position.
+struct Blit {
+    x: i64,
+    y: i64,
+    bitmap: [u8; 512],
+}
+
+/// Advancement directions carried by the four `Direction*` control codes;
+/// other control codes (e.g. [`ControlCode::VariableOffsets`]) don't move the
+/// cursor and return `None`.
+fn direction(code: ControlCode) -> Option<(i64, i64)> {
+    match code {
+        ControlCode::DirectionRightDown => Some((1, 1)),
+        ControlCode::DirectionLeftDown => Some((-1, 1)),
+        ControlCode::DirectionRightUp => Some((1, -1)),
+        ControlCode::DirectionLeftUp => Some((-1, -1)),
+        ControlCode::VariableOffsets => None,
+    }
+}
+
+/// Lay out `chars` and rasterize them into a freshly grown [`ImageBuffer`].
+pub fn render(chars: &[Character], opts: RenderOptions) -> ImageBuffer {
+    let scale = opts.scale.max(1) as i64;
+    let (mut h, mut v) = (1i64, 1i64);
+    let mut cursor_x = 0i64;
+    let mut cursor_y = 0i64;
+    let mut line_start = 0i64;
+    let mut blits = vec![];
+
+    for character in chars {
+        if let Some(code) = character.control_code {
+            if let Some((nh, nv)) = ControlCode::from_code(code).and_then(direction) {
+                h = nh;
+                v = nv;
+                line_start = cursor_x;
+            }
+            continue;
+        }
+        let Some(bitmap) = character.graphical_data else {
+            continue;
+        };
+        let dx = character.x_offset as i64;
+        let dy = character.y_offset as i64;
+        if bitmap.iter().all(|&b| b == 0) {
+            // Empty graphical data is whitespace; offsets say which kind.
+            if dy < 0 {
+                cursor_x = line_start;
+                cursor_y += v * GLYPH;
+            } else if dx > 0 {
+                cursor_x += h * GLYPH;
+            }
+            continue;
+        }
+        blits.push(Blit {
+            x: cursor_x + dx,
+            y: cursor_y + dy,
+            bitmap,
+        });
+        cursor_x += h * GLYPH;
+    }
+
+    rasterize(&blits, &opts, scale)
+}
+
+fn rasterize(blits: &[Blit], opts: &RenderOptions, scale: i64) -> ImageBuffer {
+    let channels = if opts.grayscale { 1 } else { 3 };
+    if blits.is_empty() {
+        return ImageBuffer {
+            width: 0,
+            height: 0,
+            channels,
+            data: vec![],
+        };
+    }
+
+    let min_x = blits.iter().map(|b| b.x).min().unwrap();
+    let min_y = blits.iter().map(|b| b.y).min().unwrap();
+    let max_x = blits.iter().map(|b| b.x + GLYPH).max().unwrap();
+    let max_y = blits.iter().map(|b| b.y + GLYPH).max().unwrap();
+    let width = ((max_x - min_x) * scale) as usize;
+    let height = ((max_y - min_y) * scale) as usize;
+
+    let fg = &opts.foreground[..channels];
+    let bg = &opts.background[..channels];
+    let mut data = Vec::with_capacity(width * height * channels);
+    for _ in 0..width * height {
+        data.extend_from_slice(bg);
+    }
+
+    for blit in blits {
+        let rows: Vec<u64> = blit
+            .bitmap
+            .chunks_exact(8)
+            .map(|chunk| u64::from_le_bytes(chunk.try_into().unwrap()))
+            .collect();
+        let base_x = (blit.x - min_x) * scale;
+        let base_y = (blit.y - min_y) * scale;
+        for row in 0..GLYPH {
+            for col in 0..GLYPH {
+                if (rows[row as usize] >> col) & 1 == 0 {
+                    continue;
+                }
+                for sy in 0..scale {
+                    for sx in 0..scale {
+                        let px = (base_x + col * scale + sx) as usize;
+                        let py = (base_y + row * scale + sy) as usize;
+                        let offset = (py * width + px) * channels;
+                        data[offset..offset + channels].copy_from_slice(fg);
+                    }
+                }
+            }
+        }
+    }
+
+    ImageBuffer {
+        width,
+        height,
+        channels,
+        data,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A glyph with a single point lit at row 0, column 0.
+    fn dot() -> [u8; 512] {
+        let mut bitmap = [0u8; 512];
+        bitmap[0] = 1;
+        bitmap
+    }
+
+    fn glyph(x: i16, y: i16, bitmap: [u8; 512]) -> Character {
+        Character {
+            x_offset: x,
+            y_offset: y,
+            control_code: None,
+            graphical_data: Some(bitmap),
+        }
+    }
+
+    #[test]
+    fn buffer_grows_to_fit_row() {
+        let chars = [glyph(0, 0, dot()), glyph(0, 0, dot())];
+        let img = render(&chars, RenderOptions::default());
+        assert_eq!((img.width, img.height, img.channels), (128, 64, 3));
+        assert_eq!(img.data.len(), 128 * 64 * 3);
+    }
+
+    #[test]
+    fn space_advances_cursor_without_drawing() {
+        let chars = [glyph(0, 0, dot()), glyph(1, 0, [0u8; 512]), glyph(0, 0, dot())];
+        let img = render(&chars, RenderOptions::default());
+        // The empty middle glyph leaves a one-glyph gap between the dots.
+        assert_eq!(img.width, 192);
+        assert_eq!(img.height, 64);
+    }
+
+    #[test]
+    fn newline_returns_to_line_start() {
+        let chars = [glyph(0, 0, dot()), glyph(0, -1, [0u8; 512]), glyph(0, 0, dot())];
+        let img = render(&chars, RenderOptions::default());
+        assert_eq!(img.width, 64);
+        assert_eq!(img.height, 128);
+    }
+
+    #[test]
+    fn lit_point_paints_foreground() {
+        let img = render(&[glyph(0, 0, dot())], RenderOptions::default());
+        assert_eq!(&img.data[0..3], &[0, 0, 0]);
+        assert_eq!(&img.data[3..6], &[255, 255, 255]);
+    }
+
+    #[test]
+    fn grayscale_emits_single_channel() {
+        let opts = RenderOptions {
+            grayscale: true,
+            ..RenderOptions::default()
+        };
+        let img = render(&[glyph(0, 0, dot())], opts);
+        assert_eq!(img.channels, 1);
+        assert_eq!(img.data.len(), 64 * 64);
+        assert_eq!(img.data[0], 0);
+    }
+}